@@ -16,18 +16,42 @@
 
 extern crate glob;
 extern crate libloading;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
 
 use self::glob::glob;
 use self::libloading::{Library, Symbol};
 
+use ::audio::AudioBackend;
+use ::render::RenderBackend;
+use ::window::WindowBackend;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
 use std::vec::Vec;
 
+/*================================================================================================*/
+/*------ABI VERSION CONSTANTS----------------------------------------------------------------------*/
+/*================================================================================================*/
+
+/// The ABI major version implemented by this engine.
+///
+/// A plugin is only accepted when its reported major version is equal to this value.
+pub const ABI_MAJOR: u32 = 1;
+/// The ABI minor version implemented by this engine.
+///
+/// A plugin is only accepted when its reported minor version is less than or equal to this value.
+pub const ABI_MINOR: u32 = 0;
+
 /*================================================================================================*/
 /*------PLUGINTYPE ENUM---------------------------------------------------------------------------*/
 /*================================================================================================*/
 
 /// The Plugin Type enum.
-#[derive (Copy, Clone, PartialEq, Debug)]
+#[derive (Copy, Clone, PartialEq, Debug, Deserialize)]
 pub enum PluginType {
 
     /// Used by audio backends.
@@ -73,12 +97,236 @@ pub struct Plugin {
     pub author: String,
     /// A brief description of the plugin.
     pub description: String,
+    /// The license the plugin is distributed under.
+    pub license: String,
     /// The path to the plugin.
     pub path: String,
     /// The type of plugin.
     pub plugin_type: PluginType,
+    /// The ABI `(major, minor)` version reported by the plugin.
+    pub abi_version: (u32, u32),
     /// The current state of the plugin.
-    pub plugin_state: PluginState
+    pub plugin_state: PluginState,
+    /// The configuration arguments the plugin was last loaded with, so a hot-reload can
+    /// re-apply them.
+    pub last_args: HashMap<String, String>
+}
+
+/*================================================================================================*/
+/*------PLUGINERROR ENUM--------------------------------------------------------------------------*/
+/*================================================================================================*/
+
+/// The errors which may occur while loading, unloading, or looking up a plugin.
+#[derive (Clone, PartialEq, Debug)]
+pub enum PluginError {
+
+    /// No plugin with the given name was found, or its library could not be opened or does not
+    /// provide the backend for it.
+    NotFound,
+    /// The plugin is already loaded.
+    AlreadyLoaded,
+    /// The plugin is already unloaded.
+    AlreadyUnloaded,
+    /// A dependency of the plugin is missing, or could not itself be loaded (for example
+    /// because it is disabled or part of a dependency cycle). Carries the dependency's name.
+    DependencyRequired (String),
+    /// Loading the plugin would require loading a plugin that is already being loaded further
+    /// up the call chain. Carries the name of the plugin where the cycle was detected.
+    DependencyCycle (String),
+    /// The plugin is still referenced and cannot be unloaded.
+    InUse,
+    /// The plugin is still depended on by another loaded plugin. Carries the dependent's name.
+    InUseBy (String),
+    /// The plugin's manifest does not match the metadata exported by its library.
+    ManifestMismatch (String),
+    /// The plugin is disabled, for a reason unrelated to a specific missing dependency.
+    Disabled
+}
+
+/*================================================================================================*/
+/*------PLUGINMANIFEST STRUCT---------------------------------------------------------------------*/
+/*================================================================================================*/
+
+/// A sidecar `<name>.toml` manifest describing a plugin without requiring it to be dlopen'd.
+#[derive (Clone, Deserialize)]
+struct PluginManifest {
+
+    /// The name of the plugin.
+    name: String,
+    /// The author of the plugin.
+    author: String,
+    /// A brief description of the plugin.
+    description: String,
+    /// The type of plugin.
+    plugin_type: PluginType,
+    /// The ABI `(major, minor)` version the plugin was built against.
+    abi_version: (u32, u32),
+    /// The names of the plugins this plugin depends on.
+    #[serde (default)]
+    dependencies: Vec<String>
+}
+
+/*================================================================================================*/
+/*------REGISTRATIONINFO STRUCT-------------------------------------------------------------------*/
+/*================================================================================================*/
+
+/// The metadata a plugin supplies for a single capability it registers.
+struct RegistrationInfo {
+
+    name: String,
+    author: String,
+    description: String,
+    license: String,
+    abi_version: (u32, u32)
+}
+
+/*================================================================================================*/
+/*------PLUGINREGISTRATION ENUM-------------------------------------------------------------------*/
+/*================================================================================================*/
+
+/// A single capability registered by a plugin through a `PluginRegistry`.
+enum PluginRegistration {
+
+    /// A registered audio backend.
+    Audio (RegistrationInfo, fn () -> Box<AudioBackend>),
+    /// A registered render backend.
+    Render (RegistrationInfo, fn () -> Box<RenderBackend>),
+    /// A registered window backend.
+    Window (RegistrationInfo, fn () -> Box<WindowBackend>)
+}
+
+impl PluginRegistration {
+
+    /// Returns the metadata common to every kind of registration.
+    fn info (&self) -> &RegistrationInfo {
+
+        match *self {
+            PluginRegistration::Audio (ref info, _) => info,
+            PluginRegistration::Render (ref info, _) => info,
+            PluginRegistration::Window (ref info, _) => info
+        }
+    }
+
+    /// Returns the `PluginType` this registration corresponds to.
+    fn plugin_type (&self) -> PluginType {
+
+        match *self {
+            PluginRegistration::Audio (..) => PluginType::AudioBackend,
+            PluginRegistration::Render (..) => PluginType::RenderBackend,
+            PluginRegistration::Window (..) => PluginType::WindowBackend
+        }
+    }
+}
+
+/*================================================================================================*/
+/*------PLUGINREGISTRY STRUCT----------------------------------------------------------------------*/
+/*================================================================================================*/
+
+/// Passed by `&mut` to a plugin's `register` entry point, so a single library can contribute
+/// any number of named capabilities instead of being limited to one backend of one `PluginType`.
+pub struct PluginRegistry {
+
+    _registrations: Vec<PluginRegistration>
+}
+
+impl PluginRegistry {
+
+    /// Returns a new, empty plugin registry.
+    fn new () -> PluginRegistry {
+
+        PluginRegistry {_registrations: Vec::new ()}
+    }
+
+    /// Registers a window backend capability.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the capability.
+    /// * `author` - The author of the capability.
+    /// * `description` - A brief description of the capability.
+    /// * `license` - The license the capability is distributed under.
+    /// * `abi_version` - The `(major, minor)` ABI version the capability was built against.
+    /// * `factory` - Creates a new instance of the backend.
+    pub fn register_window_backend (&mut self, name: &str, author: &str, description: &str, license: &str,
+                                    abi_version: (u32, u32), factory: fn () -> Box<WindowBackend>) {
+
+        self._registrations.push (PluginRegistration::Window (RegistrationInfo {name: name.to_owned (),
+                                                                                author: author.to_owned (),
+                                                                                description: description.to_owned (),
+                                                                                license: license.to_owned (),
+                                                                                abi_version: abi_version},
+                                                              factory));
+    }
+
+    /// Registers an audio backend capability.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the capability.
+    /// * `author` - The author of the capability.
+    /// * `description` - A brief description of the capability.
+    /// * `license` - The license the capability is distributed under.
+    /// * `abi_version` - The `(major, minor)` ABI version the capability was built against.
+    /// * `factory` - Creates a new instance of the backend.
+    pub fn register_audio_backend (&mut self, name: &str, author: &str, description: &str, license: &str,
+                                   abi_version: (u32, u32), factory: fn () -> Box<AudioBackend>) {
+
+        self._registrations.push (PluginRegistration::Audio (RegistrationInfo {name: name.to_owned (),
+                                                                               author: author.to_owned (),
+                                                                               description: description.to_owned (),
+                                                                               license: license.to_owned (),
+                                                                               abi_version: abi_version},
+                                                             factory));
+    }
+
+    /// Registers a render backend capability.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the capability.
+    /// * `author` - The author of the capability.
+    /// * `description` - A brief description of the capability.
+    /// * `license` - The license the capability is distributed under.
+    /// * `abi_version` - The `(major, minor)` ABI version the capability was built against.
+    /// * `factory` - Creates a new instance of the backend.
+    pub fn register_render_backend (&mut self, name: &str, author: &str, description: &str, license: &str,
+                                    abi_version: (u32, u32), factory: fn () -> Box<RenderBackend>) {
+
+        self._registrations.push (PluginRegistration::Render (RegistrationInfo {name: name.to_owned (),
+                                                                                author: author.to_owned (),
+                                                                                description: description.to_owned (),
+                                                                                license: license.to_owned (),
+                                                                                abi_version: abi_version},
+                                                              factory));
+    }
+}
+
+/*================================================================================================*/
+/*------LOADEDBACKEND ENUM------------------------------------------------------------------------*/
+/*================================================================================================*/
+
+/// Stores the live backend instance produced by a loaded plugin's factory symbol.
+enum LoadedBackend {
+
+    /// A loaded audio backend.
+    Audio (Box<AudioBackend>),
+    /// A loaded render backend.
+    Render (Box<RenderBackend>),
+    /// A loaded window backend.
+    Window (Box<WindowBackend>)
+}
+
+/*================================================================================================*/
+/*------LOADEDPLUGIN STRUCT-----------------------------------------------------------------------*/
+/*================================================================================================*/
+
+/// Keeps a plugin's backend instance alive alongside the `Library` it came from.
+///
+/// `backend` is declared before `_library` so that it is dropped first, ensuring the
+/// backend's vtable never outlives the library it was loaded from.
+struct LoadedPlugin {
+
+    /// The live backend instance.
+    backend: LoadedBackend,
+    /// The library the backend was created from. Kept alive for as long as the backend is.
+    _library: Library
 }
 
 /*================================================================================================*/
@@ -86,7 +334,6 @@ pub struct Plugin {
 /*================================================================================================*/
 
 /// Manages the finding and loading of plugins.
-#[derive (Clone)]
 pub struct PluginManager {
 
     // Public
@@ -94,7 +341,14 @@ pub struct PluginManager {
     pub plugin_list: Vec<Plugin>,
 
     // Private
-    _plugin_ext: String
+    _plugin_ext: String,
+    _loaded: HashMap<String, LoadedPlugin>,
+    _ref_counts: HashMap<String, u32>,
+    _dependents: HashMap<String, Vec<String>>,
+    _manifests: HashMap<String, PluginManifest>,
+    // The dependency list actually used the last time each plugin was loaded (manifest-sourced
+    // or library-sourced), so `unload_plugin` can release the same set it brought up.
+    _plugin_dependencies: HashMap<String, Vec<String>>
 }
 
 /*================================================================================================*/
@@ -119,7 +373,12 @@ impl PluginManager {
                        else {panic! ("Platform unsupported")};
 
         PluginManager {plugin_list: Vec::new (),
-                       _plugin_ext: plug_ext.to_owned ()}
+                       _plugin_ext: plug_ext.to_owned (),
+                       _loaded: HashMap::new (),
+                       _ref_counts: HashMap::new (),
+                       _dependents: HashMap::new (),
+                       _manifests: HashMap::new (),
+                       _plugin_dependencies: HashMap::new ()}
     }
 
     /// Queries the plugin directory, and stores a list of plugins.
@@ -140,33 +399,137 @@ impl PluginManager {
         for path in glob (&format! ("{}/*{}", plugin_dir, &self._plugin_ext)).unwrap ().filter_map (Result::ok) {
 
             // Load the library, and get function symbols
-            let lib = Library::new (&path).unwrap ();
+            let lib = match Library::new (&path) {
+                Ok (lib) => lib,
+                Err (err) => {
+                    warn! ("Failed to open plugin library {:?}: {}", &path, err);
+                    continue;
+                }
+            };
+
+            // A library exporting `register` is a registrar plugin: it may contribute any
+            // number of named capabilities, rather than the single get_name/get_type pair
+            // a classic plugin exports.
+            if let Ok (register) = unsafe { lib.get::<unsafe extern fn (&mut PluginRegistry)> (b"register\0") } {
+
+                let mut registry = PluginRegistry::new ();
+
+                unsafe { register (&mut registry) };
+
+                if registry._registrations.is_empty () {
+                    warn! ("Plugin {:?} exports register but registered no capabilities", &path);
+                }
+
+                for registration in registry._registrations {
+
+                    let info = registration.info ();
+
+                    let plugin_state = if info.abi_version.0 != ABI_MAJOR || info.abi_version.1 > ABI_MINOR {
+
+                        warn! ("Capability '{}' in {:?} reports ABI {}.{}, which is incompatible with this engine's ABI {}.{}; disabling",
+                               &info.name, &path, info.abi_version.0, info.abi_version.1, ABI_MAJOR, ABI_MINOR);
+
+                        PluginState::Disabled
+                    }
+
+                    else {
+                        PluginState::Unloaded
+                    };
+
+                    self.plugin_list.push (Plugin {name: info.name.clone (),
+                                                   author: info.author.clone (),
+                                                   description: info.description.clone (),
+                                                   license: info.license.clone (),
+                                                   path: path.to_str ().unwrap ().to_owned (),
+                                                   plugin_type: registration.plugin_type (),
+                                                   abi_version: info.abi_version,
+                                                   plugin_state: plugin_state,
+                                                   last_args: HashMap::new ()});
+
+                    info! ("Found capability '{}' in {:?}", &info.name, &path);
+                }
+
+                continue;
+            }
+
+            let get_name: Symbol<unsafe extern fn () -> String> = match unsafe { lib.get (b"get_name\0") } {
+                Ok (sym) => sym,
+                Err (_) => {
+                    warn! ("Plugin {:?} does not export get_name, skipping", &path);
+                    continue;
+                }
+            };
 
-            let get_name: Symbol<unsafe extern fn () -> String> = unsafe {
-                lib.get (b"get_name\0").unwrap ()
+            let get_author: Symbol<unsafe extern fn () -> String> = match unsafe { lib.get (b"get_author\0") } {
+                Ok (sym) => sym,
+                Err (_) => {
+                    warn! ("Plugin {:?} does not export get_author, skipping", &path);
+                    continue;
+                }
             };
 
-            let get_author: Symbol<unsafe extern fn () -> String> = unsafe {
-                lib.get (b"get_author\0").unwrap ()
+            let get_description: Symbol<unsafe extern fn () -> String> = match unsafe { lib.get (b"get_description\0") } {
+                Ok (sym) => sym,
+                Err (_) => {
+                    warn! ("Plugin {:?} does not export get_description, skipping", &path);
+                    continue;
+                }
             };
 
-            let get_description: Symbol<unsafe extern fn () -> String> = unsafe {
-                lib.get (b"get_description\0").unwrap ()
+            let get_license: Symbol<unsafe extern fn () -> String> = match unsafe { lib.get (b"get_license\0") } {
+                Ok (sym) => sym,
+                Err (_) => {
+                    warn! ("Plugin {:?} does not export get_license, skipping", &path);
+                    continue;
+                }
             };
 
-            let get_type: Symbol<unsafe extern fn () -> PluginType> = unsafe {
-                lib.get (b"get_type\0").unwrap ()
+            let get_type: Symbol<unsafe extern fn () -> PluginType> = match unsafe { lib.get (b"get_type\0") } {
+                Ok (sym) => sym,
+                Err (_) => {
+                    warn! ("Plugin {:?} does not export get_type, skipping", &path);
+                    continue;
+                }
+            };
+
+            // The ABI handshake is required of every plugin, so that a dlopen of a
+            // library built against a mismatched ion_core can be disabled instead of
+            // misbehaving or crashing.
+            let get_abi_version: Symbol<unsafe extern fn () -> (u32, u32)> = match unsafe { lib.get (b"get_abi_version\0") } {
+                Ok (sym) => sym,
+                Err (_) => {
+                    warn! ("Plugin {:?} does not export get_abi_version, skipping", &path);
+                    continue;
+                }
             };
 
             unsafe {
 
+                let name = get_name ();
+                let abi_version = get_abi_version ();
+
+                let plugin_state = if abi_version.0 != ABI_MAJOR || abi_version.1 > ABI_MINOR {
+
+                    warn! ("Plugin '{}' reports ABI {}.{}, which is incompatible with this engine's ABI {}.{}; disabling",
+                           &name, abi_version.0, abi_version.1, ABI_MAJOR, ABI_MINOR);
+
+                    PluginState::Disabled
+                }
+
+                else {
+                    PluginState::Unloaded
+                };
+
                 // Add the plugin to the list
-                self.plugin_list.push (Plugin {name: get_name (),
+                self.plugin_list.push (Plugin {name: name,
                                                author: get_author (),
                                                description: get_description (),
+                                               license: get_license (),
                                                path: path.to_str ().unwrap ().to_owned (),
                                                plugin_type: get_type (),
-                                               plugin_state: PluginState::Unloaded});
+                                               abi_version: abi_version,
+                                               plugin_state: plugin_state,
+                                               last_args: HashMap::new ()});
 
                 info! ("Found: {:?}", &path);
             }
@@ -183,6 +546,95 @@ impl PluginManager {
         &self.plugin_list
     }
 
+    /// Queries the plugin directory for sidecar manifests, and stores a list of plugins.
+    ///
+    /// Unlike `query_plugins`, this never dlopens a library: each `<name>.toml` manifest is
+    /// parsed on its own, and the matching library is only opened once `load_plugin` is called,
+    /// at which point its exported metadata is validated against the manifest.
+    ///
+    /// # Arguments
+    /// * `plugin_dir` - The directory which contains the plugin manifests.
+    ///
+    /// # Return value
+    /// An immutable reference to the list of plugins.
+    pub fn query_manifests (&mut self, plugin_dir: &str) -> &Vec<Plugin> {
+
+        // Clear the old plugin list
+        self.plugin_list.clear ();
+        self._manifests.clear ();
+
+        info! ("Searching for plugin manifests...");
+
+        // Recurse through all manifests in the plugin directory
+        for path in glob (&format! ("{}/*.toml", plugin_dir)).unwrap ().filter_map (Result::ok) {
+
+            let mut file = match File::open (&path) {
+                Ok (file) => file,
+                Err (err) => {
+                    warn! ("Failed to open plugin manifest {:?}: {}", &path, err);
+                    continue;
+                }
+            };
+
+            let mut contents = String::new ();
+
+            if let Err (err) = file.read_to_string (&mut contents) {
+                warn! ("Failed to read plugin manifest {:?}: {}", &path, err);
+                continue;
+            }
+
+            let manifest: PluginManifest = match toml::from_str (&contents) {
+                Ok (manifest) => manifest,
+                Err (err) => {
+                    warn! ("Failed to parse plugin manifest {:?}: {}", &path, err);
+                    continue;
+                }
+            };
+
+            // The manifest lives alongside its library under the same stem
+            let lib_path = path.with_file_name (format! ("{}{}", manifest.name, &self._plugin_ext));
+
+            // Gate on the declared ABI here too, exactly like the dlopen-discovery path above:
+            // a manifest describing a plugin built against an incompatible ion_core must not
+            // be allowed to sail through to `load_plugin` and have its factory symbol invoked.
+            let plugin_state = if manifest.abi_version.0 != ABI_MAJOR || manifest.abi_version.1 > ABI_MINOR {
+
+                warn! ("Plugin '{}' manifest declares ABI {}.{}, which is incompatible with this engine's ABI {}.{}; disabling",
+                       &manifest.name, manifest.abi_version.0, manifest.abi_version.1, ABI_MAJOR, ABI_MINOR);
+
+                PluginState::Disabled
+            }
+
+            else {
+                PluginState::Unloaded
+            };
+
+            self._manifests.insert (manifest.name.clone (), manifest.clone ());
+
+            self.plugin_list.push (Plugin {name: manifest.name,
+                                           author: manifest.author,
+                                           description: manifest.description,
+                                           license: String::new (),
+                                           path: lib_path.to_str ().unwrap ().to_owned (),
+                                           plugin_type: manifest.plugin_type,
+                                           abi_version: manifest.abi_version,
+                                           plugin_state: plugin_state,
+                                           last_args: HashMap::new ()});
+
+            info! ("Found manifest: {:?}", &path);
+        }
+
+        if self.plugin_list.is_empty () {
+            info! ("No plugin manifests found.");
+        }
+
+        else {
+            info! ("Manifest searching complete.");
+        }
+
+        &self.plugin_list
+    }
+
     /// Returns an instance of a plugin
     ///
     /// # Arguments
@@ -190,7 +642,7 @@ impl PluginManager {
     ///
     /// # Return value
     /// A result contaning a reference the plugin.
-    pub fn get_plugin (&self, name: &str) -> Result<&Plugin, ()> {
+    pub fn get_plugin (&self, name: &str) -> Result<&Plugin, PluginError> {
 
         let mut index = 0;
 
@@ -204,6 +656,675 @@ impl PluginManager {
             index += 1;
         }
 
-        Err (())
+        Err (PluginError::NotFound)
+    }
+
+    /// Loads a plugin, instantiating its backend via its factory symbol.
+    ///
+    /// Dependencies declared via the plugin's optional `get_dependencies` symbol are loaded
+    /// first, so interdependent backends always come up in a valid order.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the plugin.
+    ///
+    /// # Return value
+    /// A result indicating whether the plugin was loaded.
+    pub fn load_plugin (&mut self, name: &str) -> Result<(), PluginError> {
+
+        self.load_plugin_with_args (name, &HashMap::new ())
+    }
+
+    /// Loads a plugin exactly like `load_plugin`, but also forwards configuration arguments to
+    /// it via its optional `plugin_init` symbol.
+    ///
+    /// Absence of the `plugin_init` symbol is non-fatal; the plugin simply receives no
+    /// arguments. The arguments are remembered on the `Plugin` so a later `reload_plugin` can
+    /// re-apply them.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the plugin.
+    /// * `args` - The configuration arguments to pass to the plugin.
+    ///
+    /// # Return value
+    /// A result indicating whether the plugin was loaded.
+    pub fn load_plugin_with_args (&mut self, name: &str, args: &HashMap<String, String>) -> Result<(), PluginError> {
+
+        match self.get_plugin (name) {
+            Ok (plugin) => match plugin.plugin_state {
+                PluginState::Loaded => return Err (PluginError::AlreadyLoaded),
+                PluginState::Disabled => return Err (PluginError::Disabled),
+                _ => ()
+            },
+            Err (err) => return Err (err)
+        }
+
+        self._load_plugin (name, args)
+    }
+
+    /// Unloads and reloads a plugin, re-applying the configuration arguments it was last
+    /// loaded with.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the plugin.
+    ///
+    /// # Return value
+    /// A result indicating whether the plugin was reloaded.
+    pub fn reload_plugin (&mut self, name: &str) -> Result<(), PluginError> {
+
+        let args = match self.get_plugin (name) {
+            Ok (plugin) => plugin.last_args.clone (),
+            Err (err) => return Err (err)
+        };
+
+        if let Err (err) = self.unload_plugin (name) {
+            return Err (err);
+        }
+
+        self.load_plugin_with_args (name, &args)
+    }
+
+    /// Unloads a plugin, dropping its backend before the library it came from.
+    ///
+    /// Refuses to unload while the plugin is still depended on by another loaded plugin, or
+    /// while its reference count has not yet reached zero. Dependencies pulled in to satisfy
+    /// this plugin are released, and unloaded in turn if nothing else still needs them.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the plugin.
+    ///
+    /// # Return value
+    /// A result indicating whether the plugin was unloaded.
+    pub fn unload_plugin (&mut self, name: &str) -> Result<(), PluginError> {
+
+        let index = match self.plugin_list.iter ().position (|p| p.name == name) {
+            Some (index) => index,
+            None => return Err (PluginError::NotFound)
+        };
+
+        if self.plugin_list [index].plugin_state != PluginState::Loaded {
+            return Err (PluginError::AlreadyUnloaded);
+        }
+
+        if let Some (dependents) = self._dependents.get (name) {
+            if let Some (dependent) = dependents.first () {
+                return Err (PluginError::InUseBy (dependent.clone ()));
+            }
+        }
+
+        let remaining = {
+            let count = self._ref_counts.entry (name.to_owned ()).or_insert (0);
+            *count = count.saturating_sub (1);
+            *count
+        };
+
+        if remaining > 0 {
+            return Err (PluginError::InUse);
+        }
+
+        // Dropping the entry drops `backend` before `_library`, per `LoadedPlugin`'s field order
+        self._loaded.remove (name);
+        self._ref_counts.remove (name);
+
+        // Release this plugin's hold on its dependencies, unloading any that are no longer
+        // needed by anything else. Use the dependency list recorded at load time rather than
+        // re-deriving it here: a manifest-discovered plugin's library may not export
+        // `get_dependencies` at all, so `_read_dependencies` would silently see none.
+        let dependencies = self._plugin_dependencies.remove (name).unwrap_or_else (Vec::new);
+
+        for dependency in dependencies {
+
+            if let Some (dependents) = self._dependents.get_mut (&dependency) {
+                dependents.retain (|d| d != name);
+            }
+
+            self._release_dependency (&dependency);
+        }
+
+        self.plugin_list [index].plugin_state = PluginState::Unloaded;
+
+        info! ("Unloaded plugin '{}'", name);
+
+        Ok (())
+    }
+
+    /// Returns the live window backend for a loaded plugin, if any.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the plugin.
+    ///
+    /// # Return value
+    /// A mutable reference to the window backend, or `None` if the plugin is not a loaded window backend.
+    pub fn get_window_backend (&mut self, name: &str) -> Option<&mut WindowBackend> {
+
+        match self._loaded.get_mut (name) {
+            Some (loaded) => match loaded.backend {
+                LoadedBackend::Window (ref mut backend) => Some (backend.as_mut ()),
+                _ => None
+            },
+            None => None
+        }
+    }
+
+    /// Returns the live audio backend for a loaded plugin, if any.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the plugin.
+    ///
+    /// # Return value
+    /// A mutable reference to the audio backend, or `None` if the plugin is not a loaded audio backend.
+    pub fn get_audio_backend (&mut self, name: &str) -> Option<&mut AudioBackend> {
+
+        match self._loaded.get_mut (name) {
+            Some (loaded) => match loaded.backend {
+                LoadedBackend::Audio (ref mut backend) => Some (backend.as_mut ()),
+                _ => None
+            },
+            None => None
+        }
+    }
+
+    /// Returns the live render backend for a loaded plugin, if any.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the plugin.
+    ///
+    /// # Return value
+    /// A mutable reference to the render backend, or `None` if the plugin is not a loaded render backend.
+    pub fn get_render_backend (&mut self, name: &str) -> Option<&mut RenderBackend> {
+
+        match self._loaded.get_mut (name) {
+            Some (loaded) => match loaded.backend {
+                LoadedBackend::Render (ref mut backend) => Some (backend.as_mut ()),
+                _ => None
+            },
+            None => None
+        }
+    }
+}
+
+/*================================================================================================*/
+/*------PLUGINMANAGER PRIVATE MEMBERS-------------------------------------------------------------*/
+/*================================================================================================*/
+
+impl PluginManager {
+
+    /// Loads a plugin and its dependencies, recursively. An already-loaded plugin is not an
+    /// error here: it simply has its reference count bumped. A disabled plugin is refused, and a
+    /// plugin still being loaded further up the call chain is reported as a dependency cycle.
+    /// Dependencies are not given any arguments.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the plugin.
+    /// * `args` - The configuration arguments to pass to the plugin via `plugin_init`.
+    ///
+    /// # Return value
+    /// A result indicating whether the plugin was loaded.
+    fn _load_plugin (&mut self, name: &str, args: &HashMap<String, String>) -> Result<(), PluginError> {
+
+        let index = match self.plugin_list.iter ().position (|p| p.name == name) {
+            Some (index) => index,
+            None => return Err (PluginError::NotFound)
+        };
+
+        if self.plugin_list [index].plugin_state == PluginState::Loaded {
+            *self._ref_counts.entry (name.to_owned ()).or_insert (0) += 1;
+            return Ok (());
+        }
+
+        if self.plugin_list [index].plugin_state == PluginState::Disabled {
+            return Err (PluginError::Disabled);
+        }
+
+        // A plugin still `MarkedForLoad` is further up this very call chain: loading it again
+        // here would recurse forever, so treat re-entry as a dependency cycle instead.
+        if self.plugin_list [index].plugin_state == PluginState::MarkedForLoad {
+            return Err (PluginError::DependencyCycle (name.to_owned ()));
+        }
+
+        self.plugin_list [index].plugin_state = PluginState::MarkedForLoad;
+
+        let path = self.plugin_list [index].path.clone ();
+        let plugin_type = self.plugin_list [index].plugin_type;
+        let manifest = self._manifests.get (name).cloned ();
+
+        // A manifest-discovered plugin already declares its dependencies; otherwise fall
+        // back to reading them straight off the (not yet opened) library
+        let dependencies = match manifest {
+            Some (ref manifest) => manifest.dependencies.clone (),
+            None => self._read_dependencies (&path)
+        };
+
+        // Remember exactly which dependencies this load brought up, so `unload_plugin` can
+        // release the same set regardless of whether it came from a manifest or the library
+        self._plugin_dependencies.insert (name.to_owned (), dependencies.clone ());
+
+        // Bring up dependencies before this plugin, so interdependent backends always come up
+        // in a valid order. A dependency that fails to resolve only bricks this plugin's own
+        // attempt, not the whole dependency tree: roll back whatever was already acquired
+        // earlier in this same attempt, and leave this plugin `Unloaded` rather than
+        // `Disabled` so the caller can simply retry once the missing dependency is available.
+        let mut acquired = Vec::new ();
+
+        for dependency in &dependencies {
+
+            if self.plugin_list.iter ().find (|p| &p.name == dependency).is_none () {
+                self._rollback_dependencies (name, &acquired);
+                self.plugin_list [index].plugin_state = PluginState::Unloaded;
+                return Err (PluginError::DependencyRequired (dependency.clone ()));
+            }
+
+            if let Err (_) = self._load_plugin (dependency, &HashMap::new ()) {
+                self._rollback_dependencies (name, &acquired);
+                self.plugin_list [index].plugin_state = PluginState::Unloaded;
+                return Err (PluginError::DependencyRequired (dependency.clone ()));
+            }
+
+            self._dependents.entry (dependency.clone ()).or_insert_with (Vec::new).push (name.to_owned ());
+            acquired.push (dependency.clone ());
+        }
+
+        let lib = match Library::new (&path) {
+            Ok (lib) => lib,
+            Err (err) => {
+                warn! ("Failed to open plugin library {:?} for loading: {}", &path, err);
+                self._rollback_dependencies (name, &dependencies);
+                self.plugin_list [index].plugin_state = PluginState::Disabled;
+                return Err (PluginError::NotFound);
+            }
+        };
+
+        if let Some (manifest) = manifest {
+            if let Err (reason) = self._validate_manifest (&lib, &manifest) {
+                warn! ("Plugin '{}' manifest does not match its library: {}", name, &reason);
+                self._rollback_dependencies (name, &dependencies);
+                self.plugin_list [index].plugin_state = PluginState::Disabled;
+                return Err (PluginError::ManifestMismatch (reason));
+            }
+        }
+
+        let backend = unsafe {
+
+            // A registrar plugin is re-registered fresh against this now-retained library, so
+            // the factory fn pointer handed back is valid for as long as the library is.
+            if let Ok (register) = lib.get::<unsafe extern fn (&mut PluginRegistry)> (b"register\0") {
+
+                let mut registry = PluginRegistry::new ();
+                register (&mut registry);
+
+                let registration = match registry._registrations.into_iter ().find (|r| r.info ().name == name) {
+                    Some (registration) => registration,
+                    None => {
+                        warn! ("Plugin '{}' was not re-registered by its library on load", name);
+                        self._rollback_dependencies (name, &dependencies);
+                        self.plugin_list [index].plugin_state = PluginState::Disabled;
+                        return Err (PluginError::NotFound);
+                    }
+                };
+
+                match registration {
+                    PluginRegistration::Audio (_, factory) => LoadedBackend::Audio (factory ()),
+                    PluginRegistration::Render (_, factory) => LoadedBackend::Render (factory ()),
+                    PluginRegistration::Window (_, factory) => LoadedBackend::Window (factory ())
+                }
+            }
+
+            else {
+
+                match plugin_type {
+
+                    PluginType::AudioBackend => {
+
+                        let create: Symbol<unsafe extern fn () -> *mut AudioBackend> = match lib.get (b"create_audio_backend\0") {
+                            Ok (sym) => sym,
+                            Err (_) => {
+                                warn! ("Plugin '{}' does not export create_audio_backend", name);
+                                self._rollback_dependencies (name, &dependencies);
+                                self.plugin_list [index].plugin_state = PluginState::Disabled;
+                                return Err (PluginError::NotFound);
+                            }
+                        };
+
+                        LoadedBackend::Audio (Box::from_raw (create ()))
+                    }
+
+                    PluginType::RenderBackend => {
+
+                        let create: Symbol<unsafe extern fn () -> *mut RenderBackend> = match lib.get (b"create_render_backend\0") {
+                            Ok (sym) => sym,
+                            Err (_) => {
+                                warn! ("Plugin '{}' does not export create_render_backend", name);
+                                self._rollback_dependencies (name, &dependencies);
+                                self.plugin_list [index].plugin_state = PluginState::Disabled;
+                                return Err (PluginError::NotFound);
+                            }
+                        };
+
+                        LoadedBackend::Render (Box::from_raw (create ()))
+                    }
+
+                    PluginType::WindowBackend => {
+
+                        let create: Symbol<unsafe extern fn () -> *mut WindowBackend> = match lib.get (b"create_window_backend\0") {
+                            Ok (sym) => sym,
+                            Err (_) => {
+                                warn! ("Plugin '{}' does not export create_window_backend", name);
+                                self._rollback_dependencies (name, &dependencies);
+                                self.plugin_list [index].plugin_state = PluginState::Disabled;
+                                return Err (PluginError::NotFound);
+                            }
+                        };
+
+                        LoadedBackend::Window (Box::from_raw (create ()))
+                    }
+                }
+            }
+        };
+
+        // Configuration is optional: a plugin that doesn't export `plugin_init` simply
+        // receives no arguments
+        if let Ok (plugin_init) = unsafe { lib.get::<unsafe extern fn (&HashMap<String, String>)> (b"plugin_init\0") } {
+            unsafe { plugin_init (args) };
+        }
+
+        self._loaded.insert (name.to_owned (), LoadedPlugin {backend: backend,
+                                                             _library: lib});
+
+        *self._ref_counts.entry (name.to_owned ()).or_insert (0) += 1;
+        self.plugin_list [index].plugin_state = PluginState::Loaded;
+        self.plugin_list [index].last_args = args.clone ();
+
+        info! ("Loaded plugin '{}'", name);
+
+        Ok (())
+    }
+
+    /// Releases one dependent's hold on a dependency, decrementing its reference count and
+    /// tearing it down - recursively releasing its own dependencies in turn - once nothing
+    /// else needs it.
+    ///
+    /// Unlike `unload_plugin`, this always accounts for the release even while other
+    /// dependents remain: it mirrors the implicit "bump" `_load_plugin` gives a dependency it
+    /// loads or re-uses on a consumer's behalf, so a dependency shared by several plugins is
+    /// only actually torn down once every one of them has given back its share.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the dependency being released.
+    fn _release_dependency (&mut self, name: &str) {
+
+        let remaining = {
+            let count = self._ref_counts.entry (name.to_owned ()).or_insert (0);
+            *count = count.saturating_sub (1);
+            *count
+        };
+
+        if remaining > 0 {
+            return;
+        }
+
+        if let Some (dependents) = self._dependents.get (name) {
+            if !dependents.is_empty () {
+                return;
+            }
+        }
+
+        let index = match self.plugin_list.iter ().position (|p| p.name == name) {
+            Some (index) => index,
+            None => return
+        };
+
+        if self.plugin_list [index].plugin_state != PluginState::Loaded {
+            return;
+        }
+
+        // Dropping the entry drops `backend` before `_library`, per `LoadedPlugin`'s field order
+        self._loaded.remove (name);
+        self._ref_counts.remove (name);
+
+        let dependencies = self._plugin_dependencies.remove (name).unwrap_or_else (Vec::new);
+
+        for dependency in dependencies {
+
+            if let Some (dependents) = self._dependents.get_mut (&dependency) {
+                dependents.retain (|d| d != name);
+            }
+
+            self._release_dependency (&dependency);
+        }
+
+        self.plugin_list [index].plugin_state = PluginState::Unloaded;
+
+        info! ("Unloaded plugin '{}'", name);
+    }
+
+    /// Releases dependencies already acquired earlier in a load attempt that ultimately
+    /// failed, and forgets the dependency list recorded for this plugin, so a failed load
+    /// never leaks a reference count or leaves stale `_dependents`/`_plugin_dependencies`
+    /// bookkeeping behind.
+    ///
+    /// # Arguments
+    /// * `name` - The name of the plugin whose load attempt is being rolled back.
+    /// * `acquired` - The dependencies successfully acquired so far in that attempt.
+    fn _rollback_dependencies (&mut self, name: &str, acquired: &[String]) {
+
+        self._plugin_dependencies.remove (name);
+
+        for dependency in acquired {
+
+            if let Some (dependents) = self._dependents.get_mut (dependency) {
+                dependents.retain (|d| d != name);
+            }
+
+            self._release_dependency (dependency);
+        }
+    }
+
+    /// Reads a plugin's optional dependency list.
+    ///
+    /// Absence of the `get_dependencies` symbol is non-fatal; the plugin is simply treated as
+    /// having no dependencies.
+    ///
+    /// # Arguments
+    /// * `path` - The path to the plugin library.
+    ///
+    /// # Return value
+    /// The names of the plugins this plugin depends on.
+    fn _read_dependencies (&self, path: &str) -> Vec<String> {
+
+        let lib = match Library::new (path) {
+            Ok (lib) => lib,
+            Err (_) => return Vec::new ()
+        };
+
+        let get_dependencies: Symbol<unsafe extern fn () -> Vec<String>> = match unsafe { lib.get (b"get_dependencies\0") } {
+            Ok (sym) => sym,
+            Err (_) => return Vec::new ()
+        };
+
+        unsafe { get_dependencies () }
+    }
+
+    /// Validates that a manifest's declared metadata matches what its library actually exports.
+    ///
+    /// # Arguments
+    /// * `lib` - The opened plugin library.
+    /// * `manifest` - The manifest read alongside the library.
+    ///
+    /// # Return value
+    /// `Ok` if the manifest matches, otherwise an `Err` describing the first mismatch found.
+    fn _validate_manifest (&self, lib: &Library, manifest: &PluginManifest) -> Result<(), String> {
+
+        let get_name: Symbol<unsafe extern fn () -> String> = match unsafe { lib.get (b"get_name\0") } {
+            Ok (sym) => sym,
+            Err (_) => return Err ("library does not export get_name".to_owned ())
+        };
+
+        let get_author: Symbol<unsafe extern fn () -> String> = match unsafe { lib.get (b"get_author\0") } {
+            Ok (sym) => sym,
+            Err (_) => return Err ("library does not export get_author".to_owned ())
+        };
+
+        let get_description: Symbol<unsafe extern fn () -> String> = match unsafe { lib.get (b"get_description\0") } {
+            Ok (sym) => sym,
+            Err (_) => return Err ("library does not export get_description".to_owned ())
+        };
+
+        let get_type: Symbol<unsafe extern fn () -> PluginType> = match unsafe { lib.get (b"get_type\0") } {
+            Ok (sym) => sym,
+            Err (_) => return Err ("library does not export get_type".to_owned ())
+        };
+
+        let get_abi_version: Symbol<unsafe extern fn () -> (u32, u32)> = match unsafe { lib.get (b"get_abi_version\0") } {
+            Ok (sym) => sym,
+            Err (_) => return Err ("library does not export get_abi_version".to_owned ())
+        };
+
+        unsafe {
+
+            if get_name () != manifest.name {
+                return Err ("name does not match the manifest".to_owned ());
+            }
+
+            if get_author () != manifest.author {
+                return Err ("author does not match the manifest".to_owned ());
+            }
+
+            if get_description () != manifest.description {
+                return Err ("description does not match the manifest".to_owned ());
+            }
+
+            if get_type () != manifest.plugin_type {
+                return Err ("plugin_type does not match the manifest".to_owned ());
+            }
+
+            if get_abi_version () != manifest.abi_version {
+                return Err ("abi_version does not match the manifest".to_owned ());
+            }
+        }
+
+        // `query_manifests` already disables a plugin whose manifest declares an incompatible
+        // ABI, but that state can be stale (the manifest changed, or this plugin was inserted
+        // directly without going through discovery): re-check here, right before the factory
+        // symbol would otherwise be invoked.
+        if manifest.abi_version.0 != ABI_MAJOR || manifest.abi_version.1 > ABI_MINOR {
+            return Err (format! ("ABI {}.{} is incompatible with this engine's ABI {}.{}",
+                                 manifest.abi_version.0, manifest.abi_version.1, ABI_MAJOR, ABI_MINOR));
+        }
+
+        Ok (())
+    }
+}
+
+/*================================================================================================*/
+/*------TESTS--------------------------------------------------------------------------------------*/
+/*================================================================================================*/
+
+#[cfg (test)]
+mod tests {
+
+    use super::*;
+
+    /// Builds a `Plugin` entry directly, bypassing discovery, so the state machine can be
+    /// exercised without a real plugin library to dlopen.
+    fn dummy_plugin (name: &str, state: PluginState) -> Plugin {
+
+        Plugin {name: name.to_owned (),
+                author: String::new (),
+                description: String::new (),
+                license: String::new (),
+                path: String::new (),
+                plugin_type: PluginType::WindowBackend,
+                abi_version: (ABI_MAJOR, ABI_MINOR),
+                plugin_state: state,
+                last_args: HashMap::new ()}
+    }
+
+    /// Builds a manifest declaring the given dependencies, bypassing the filesystem.
+    fn dummy_manifest (name: &str, dependencies: Vec<String>) -> PluginManifest {
+
+        PluginManifest {name: name.to_owned (),
+                        author: String::new (),
+                        description: String::new (),
+                        plugin_type: PluginType::WindowBackend,
+                        abi_version: (ABI_MAJOR, ABI_MINOR),
+                        dependencies: dependencies}
+    }
+
+    #[test]
+    fn load_plugin_bumps_ref_count_for_an_already_loaded_plugin () {
+
+        let mut manager = PluginManager::new ();
+        manager.plugin_list.push (dummy_plugin ("shared", PluginState::Loaded));
+
+        assert_eq! (manager._load_plugin ("shared", &HashMap::new ()), Ok (()));
+        assert_eq! (manager._load_plugin ("shared", &HashMap::new ()), Ok (()));
+
+        assert_eq! (manager._ref_counts.get ("shared"), Some (&2));
+    }
+
+    #[test]
+    fn unload_plugin_refuses_while_still_depended_on () {
+
+        let mut manager = PluginManager::new ();
+        manager.plugin_list.push (dummy_plugin ("dependency", PluginState::Loaded));
+
+        manager._dependents.insert ("dependency".to_owned (), vec! ["dependent".to_owned ()]);
+
+        assert_eq! (manager.unload_plugin ("dependency"), Err (PluginError::InUseBy ("dependent".to_owned ())));
+    }
+
+    #[test]
+    fn unload_plugin_releases_a_shared_dependency_only_once_every_dependent_is_gone () {
+
+        let mut manager = PluginManager::new ();
+        manager.plugin_list.push (dummy_plugin ("dependency", PluginState::Loaded));
+        manager.plugin_list.push (dummy_plugin ("consumer_a", PluginState::Loaded));
+        manager.plugin_list.push (dummy_plugin ("consumer_b", PluginState::Loaded));
+
+        // Simulate consumer_a and consumer_b each having pulled in the same dependency at load
+        // time, exactly as `_load_plugin`'s dependency loop would have recorded it.
+        manager._ref_counts.insert ("dependency".to_owned (), 2);
+        manager._dependents.insert ("dependency".to_owned (), vec! ["consumer_a".to_owned (), "consumer_b".to_owned ()]);
+        manager._plugin_dependencies.insert ("consumer_a".to_owned (), vec! ["dependency".to_owned ()]);
+        manager._plugin_dependencies.insert ("consumer_b".to_owned (), vec! ["dependency".to_owned ()]);
+
+        // consumer_a unloads: the dependency is still needed by consumer_b, so it stays loaded
+        assert_eq! (manager.unload_plugin ("consumer_a"), Ok (()));
+        assert! (manager.get_plugin ("dependency").unwrap ().plugin_state == PluginState::Loaded);
+        assert_eq! (manager.unload_plugin ("dependency"), Err (PluginError::InUseBy ("consumer_b".to_owned ())));
+
+        // consumer_b unloads: nothing depends on the dependency any more, so it is released too
+        assert_eq! (manager.unload_plugin ("consumer_b"), Ok (()));
+        assert! (manager.get_plugin ("dependency").unwrap ().plugin_state == PluginState::Unloaded);
+        assert_eq! (manager._ref_counts.get ("dependency"), None);
+    }
+
+    #[test]
+    fn load_plugin_rejects_a_plugin_already_being_loaded_further_up_the_chain () {
+
+        let mut manager = PluginManager::new ();
+        manager.plugin_list.push (dummy_plugin ("a", PluginState::MarkedForLoad));
+
+        assert_eq! (manager._load_plugin ("a", &HashMap::new ()), Err (PluginError::DependencyCycle ("a".to_owned ())));
+    }
+
+    #[test]
+    fn load_plugin_terminates_on_a_circular_manifest_dependency_instead_of_recursing_forever () {
+
+        let mut manager = PluginManager::new ();
+        manager.plugin_list.push (dummy_plugin ("a", PluginState::Unloaded));
+        manager.plugin_list.push (dummy_plugin ("b", PluginState::Unloaded));
+
+        manager._manifests.insert ("a".to_owned (), dummy_manifest ("a", vec! ["b".to_owned ()]));
+        manager._manifests.insert ("b".to_owned (), dummy_manifest ("b", vec! ["a".to_owned ()]));
+
+        // The cycle is caught several calls down the chain and bubbles up as a missing
+        // dependency, rather than recursing until the stack overflows.
+        assert_eq! (manager._load_plugin ("a", &HashMap::new ()), Err (PluginError::DependencyRequired ("b".to_owned ())));
+
+        // Neither plugin is left stuck `MarkedForLoad`, nor holding a bogus reference to the other
+        assert! (manager.get_plugin ("a").unwrap ().plugin_state == PluginState::Unloaded);
+        assert! (manager.get_plugin ("b").unwrap ().plugin_state == PluginState::Unloaded);
+        assert! (manager._dependents.get ("a").map_or (true, |d| d.is_empty ()));
+        assert! (manager._dependents.get ("b").map_or (true, |d| d.is_empty ()));
     }
 }
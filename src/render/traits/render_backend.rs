@@ -0,0 +1,35 @@
+/*===============================================================================================*/
+// Copyright 2016 Kyle Finlay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+/*===============================================================================================*/
+
+/*===============================================================================================*/
+/*------RENDER BACKEND TRAIT-----------------------------------------------------------------------*/
+/*===============================================================================================*/
+
+/// Used for backend agnostic rendering.
+///
+/// Render backend plugins implement this trait. The backend is then accessed by the
+/// Render Manager.
+pub trait RenderBackend {
+
+    /// Initializes the render backend.
+    fn init (&mut self);
+    /// On pre render event.
+    fn on_pre_render (&mut self);
+    /// On render event.
+    fn on_render (&mut self);
+    /// On post render event.
+    fn on_post_render (&mut self);
+}